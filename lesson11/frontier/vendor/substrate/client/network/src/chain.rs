@@ -0,0 +1,45 @@
+// Copyright 2017-2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+//
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Blockchain access trait.
+
+use sp_runtime::traits::Block as BlockT;
+
+/// Error type used by the finality proof provider.
+pub type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Provides finality proofs for blocks.
+pub trait FinalityProofProvider<Block: BlockT>: Send + Sync {
+	/// Prove finality of the block identified by `for_block`.
+	///
+	/// Returns `None` if no proof is available for the requested block.
+	fn prove_finality(
+		&self,
+		for_block: Block::Hash,
+		request: &[u8],
+	) -> Result<Option<Vec<u8>>, Error>;
+
+	/// Prove that `block_number` is canonical via the CHT at `cht_index`.
+	///
+	/// The returned nodes form the Merkle path from the stored CHT root down to
+	/// the `block_number => header_hash` leaf. Returns `None` when the CHT range
+	/// is not yet a complete, finalized range and therefore cannot be proven.
+	fn prove_canonical(
+		&self,
+		cht_index: u64,
+		block_number: u32,
+	) -> Result<Option<Vec<Vec<u8>>>, Error>;
+}
@@ -55,19 +55,27 @@ use prost::Message;
 use sp_runtime::{generic::BlockId, traits::{Block, Header, One, Zero}};
 use std::{
 	cmp::min,
-	collections::VecDeque,
+	collections::{HashMap, VecDeque},
 	io,
 	iter,
 	marker::PhantomData,
 	sync::Arc,
-	time::Duration,
+	time::{Duration, Instant},
 	task::{Context, Poll}
 };
 use void::{Void, unreachable};
+use wasm_timer::Delay;
 
 // Type alias for convenience.
 pub type Error = Box<dyn std::error::Error + 'static>;
 
+/// Number of blocks covered by a single Canonical Hash Trie.
+///
+/// A CHT root is only built once a full, finalized range of this many blocks is
+/// available; requests for a block inside an incomplete range are answered with
+/// an explicit "not available" (empty) response.
+pub const CHT_SIZE: u64 = 2048;
+
 /// Event generated by the finality proof requests behaviour.
 #[derive(Debug)]
 pub enum Event<B: Block> {
@@ -79,6 +87,48 @@ pub enum Event<B: Block> {
 		/// Finality proof returned by the remote.
 		proof: Vec<u8>,
 	},
+	/// A CHT-anchored proof that a block number is canonical has arrived.
+	ChtProof {
+		peer: PeerId,
+		/// Block number the proof is anchored to.
+		block_number: u32,
+		/// Merkle path nodes from the CHT root down to the `number => hash` leaf.
+		///
+		/// Empty if the remote could not anchor the number to a completed CHT.
+		cht_proof: Vec<Vec<u8>>,
+	},
+	/// An outstanding request did not receive a response in time.
+	///
+	/// The sync layer can react by retrying the proof against a different peer.
+	Failure {
+		peer: PeerId,
+		/// Block hash of the request that timed out.
+		block_hash: B::Hash,
+		/// Human-readable cause of the failure.
+		error: Error,
+	},
+	/// An outstanding CHT-anchored request did not receive a response in time.
+	ChtFailure {
+		peer: PeerId,
+		/// Block number of the CHT request that timed out.
+		block_number: u32,
+		/// Human-readable cause of the failure.
+		error: Error,
+	},
+}
+
+/// Negotiated wire-format version of the finality proof protocol.
+pub type ProtocolVersion = u32;
+
+/// Parse the trailing version number out of a `/<id>/finality-proof/<n>` name.
+///
+/// Falls back to version `1` for names that do not carry a recognisable suffix.
+fn protocol_version(info: &[u8]) -> ProtocolVersion {
+	let n = info.iter().rev().take_while(|b| b.is_ascii_digit()).count();
+	std::str::from_utf8(&info[info.len() - n..])
+		.ok()
+		.and_then(|s| s.parse().ok())
+		.unwrap_or(1)
 }
 
 /// Configuration options for `FinalityProofRequests`.
@@ -87,7 +137,8 @@ pub struct Config {
 	max_request_len: usize,
 	max_response_len: usize,
 	inactivity_timeout: Duration,
-	protocol: Bytes,
+	/// Supported protocol names, ordered from most to least preferred.
+	protocols: Vec<Bytes>,
 }
 
 impl Config {
@@ -101,7 +152,7 @@ impl Config {
 			max_request_len: 1024 * 1024,
 			max_response_len: 1024 * 1024,
 			inactivity_timeout: Duration::from_secs(15),
-			protocol: Bytes::new(),
+			protocols: Vec::new(),
 		};
 		c.set_protocol(id);
 		c
@@ -125,13 +176,21 @@ impl Config {
 		self
 	}
 
-	/// Set protocol to use for upgrade negotiation.
+	/// Set the protocols to use for upgrade negotiation.
+	///
+	/// The newest wire-format (`/finality-proof/2`) is advertised first and is
+	/// therefore preferred, with `/finality-proof/1` offered as a fallback so
+	/// the format can evolve without a hard network split.
 	pub fn set_protocol(&mut self, id: &ProtocolId) -> &mut Self {
-		let mut v = Vec::new();
-		v.extend_from_slice(b"/");
-		v.extend_from_slice(id.as_bytes());
-		v.extend_from_slice(b"/finality-proof/1");
-		self.protocol = v.into();
+		let name = |version: &[u8]| -> Bytes {
+			let mut v = Vec::new();
+			v.extend_from_slice(b"/");
+			v.extend_from_slice(id.as_bytes());
+			v.extend_from_slice(b"/finality-proof/");
+			v.extend_from_slice(version);
+			v.into()
+		};
+		self.protocols = vec![name(b"2"), name(b"1")];
 		self
 	}
 }
@@ -146,6 +205,19 @@ pub struct FinalityProofRequests<B: Block> {
 	outgoing: FuturesUnordered<BoxFuture<'static, ()>>,
 	/// Events to return as soon as possible from `poll`.
 	pending_events: VecDeque<NetworkBehaviourAction<OutboundProtocol<B>, Event<B>>>,
+	/// Outstanding requests awaiting a response, keyed by the requested block hash.
+	///
+	/// Each entry records the peer the request was sent to and the instant after
+	/// which the request is considered timed out.
+	pending: HashMap<B::Hash, (PeerId, Instant)>,
+	/// Outstanding CHT-anchored requests, keyed by `(peer, block_number)`.
+	///
+	/// Tracked separately from `pending` because CHT requests carry no block
+	/// hash and a zero/default hash would otherwise alias a genuine entry.
+	pending_cht: HashMap<(PeerId, u32), Instant>,
+	/// Timer armed against the earliest pending deadline so `poll` is woken when
+	/// a request expires, together with the deadline it is currently armed for.
+	timeout: Option<(Instant, Delay)>,
 }
 
 impl<B> FinalityProofRequests<B>
@@ -162,6 +234,9 @@ where
 			finality_proof_provider,
 			outgoing: FuturesUnordered::new(),
 			pending_events: VecDeque::new(),
+			pending: HashMap::new(),
+			pending_cht: HashMap::new(),
+			timeout: None,
 		}
 	}
 
@@ -173,6 +248,10 @@ where
 		let protobuf_rq = schema::v1::finality::FinalityProofRequest {
 			block_hash: block_hash.encode(),
 			request,
+			// A full-justification request; CHT requests set `is_cht` and carry a
+			// block number via `send_cht_request`.
+			is_cht: false,
+			block_number: 0,
 		};
 
 		let mut buf = Vec::with_capacity(protobuf_rq.encoded_len());
@@ -182,6 +261,8 @@ where
 		}
 
 		log::trace!("enqueueing finality proof request to {:?}: {:?}", target, protobuf_rq);
+		let deadline = Instant::now() + self.config.inactivity_timeout;
+		self.pending.insert(block_hash, (target.clone(), deadline));
 		self.pending_events.push_back(NetworkBehaviourAction::NotifyHandler {
 			peer_id: target.clone(),
 			handler: NotifyHandler::Any,
@@ -189,30 +270,93 @@ where
 				request: buf,
 				block_hash,
 				max_response_size: self.config.max_response_len,
-				protocol: self.config.protocol.clone(),
+				protocols: self.config.protocols.clone(),
+			},
+		});
+	}
+
+	/// Issue a CHT-anchored request asking a peer to prove that `block_number`
+	/// is canonical without transferring a full justification.
+	pub fn send_cht_request(&mut self, target: &PeerId, block_number: u32) {
+		let protobuf_rq = schema::v1::finality::FinalityProofRequest {
+			block_hash: Vec::new(),
+			request: Vec::new(),
+			is_cht: true,
+			block_number,
+		};
+
+		let mut buf = Vec::with_capacity(protobuf_rq.encoded_len());
+		if let Err(err) = protobuf_rq.encode(&mut buf) {
+			log::warn!("failed to encode CHT proof request {:?}: {:?}", protobuf_rq, err);
+			return;
+		}
+
+		log::trace!("enqueueing CHT proof request to {:?} for #{}", target, block_number);
+		let deadline = Instant::now() + self.config.inactivity_timeout;
+		self.pending_cht.insert((target.clone(), block_number), deadline);
+		self.pending_events.push_back(NetworkBehaviourAction::NotifyHandler {
+			peer_id: target.clone(),
+			handler: NotifyHandler::Any,
+			event: OutboundProtocol {
+				request: buf,
+				block_hash: Default::default(),
+				max_response_size: self.config.max_response_len,
+				protocols: self.config.protocols.clone(),
 			},
 		});
 	}
 
 	/// Callback, invoked when a new finality request has been received from remote.
-	fn on_finality_request(&mut self, peer: &PeerId, request: &schema::v1::finality::FinalityProofRequest)
+	fn on_finality_request(
+		&mut self,
+		peer: &PeerId,
+		request: &schema::v1::finality::FinalityProofRequest,
+		version: ProtocolVersion,
+	)
 		-> Result<schema::v1::finality::FinalityProofResponse, Error>
 	{
-		let block_hash = Decode::decode(&mut request.block_hash.as_ref())?;
-
-		log::trace!(target: "sync", "Finality proof request from {} for {}", peer, block_hash);
-
-		// Note that an empty Vec is sent if no proof is available.
-		let finality_proof = if let Some(provider) = &self.finality_proof_provider {
+		let provider = if let Some(provider) = &self.finality_proof_provider {
 			provider
-				.prove_finality(block_hash, &request.request)?
-				.unwrap_or(Vec::new())
 		} else {
 			log::error!("Answering a finality proof request while finality provider is empty");
 			return Err(From::from("Empty finality proof provider".to_string()))
 		};
 
-		Ok(schema::v1::finality::FinalityProofResponse { proof: finality_proof })
+		// CHT-anchored requests are only understood from version 2 onwards.
+		if request.is_cht && version >= 2 {
+			let number = request.block_number;
+			let cht_index = u64::from(number) / CHT_SIZE;
+			log::trace!(target: "sync", "CHT proof request from {} for #{} (cht {})", peer, number, cht_index);
+
+			// Only completed, finalized CHT ranges can be served. If the range is
+			// incomplete we return an empty proof rather than a partial path.
+			let cht_proof = provider
+				.prove_canonical(cht_index, number)?
+				.unwrap_or_default();
+
+			return Ok(schema::v1::finality::FinalityProofResponse {
+				proof: Vec::new(),
+				is_cht: true,
+				block_number: number,
+				cht_proof,
+			});
+		}
+
+		let block_hash = Decode::decode(&mut request.block_hash.as_ref())?;
+
+		log::trace!(target: "sync", "Finality proof request from {} for {}", peer, block_hash);
+
+		// Note that an empty Vec is sent if no proof is available.
+		let finality_proof = provider
+			.prove_finality(block_hash, &request.request)?
+			.unwrap_or(Vec::new());
+
+		Ok(schema::v1::finality::FinalityProofResponse {
+			proof: finality_proof,
+			is_cht: false,
+			block_number: 0,
+			cht_proof: Vec::new(),
+		})
 	}
 }
 
@@ -226,10 +370,12 @@ where
 	fn new_handler(&mut self) -> Self::ProtocolsHandler {
 		let p = InboundProtocol {
 			max_request_len: self.config.max_request_len,
-			protocol: if self.finality_proof_provider.is_some() {
-				Some(self.config.protocol.clone())
+			// Advertise every supported protocol, or none at all when this node
+			// cannot serve finality proofs.
+			protocols: if self.finality_proof_provider.is_some() {
+				self.config.protocols.clone()
 			} else {
-				None
+				Vec::new()
 			},
 			marker: PhantomData,
 		};
@@ -245,7 +391,11 @@ where
 	fn inject_connected(&mut self, _peer: &PeerId) {
 	}
 
-	fn inject_disconnected(&mut self, _peer: &PeerId) {
+	fn inject_disconnected(&mut self, peer: &PeerId) {
+		// Drop any outstanding requests to a peer that just went away; the sync
+		// layer will notice the missing response and retry elsewhere.
+		self.pending.retain(|_, (p, _)| p != peer);
+		self.pending_cht.retain(|(p, _), _| p != peer);
 	}
 
 	fn inject_event(
@@ -255,8 +405,8 @@ where
 		event: NodeEvent<B, NegotiatedSubstream>
 	) {
 		match event {
-			NodeEvent::Request(request, mut stream) => {
-				match self.on_finality_request(&peer, &request) {
+			NodeEvent::Request(request, mut stream, version) => {
+				match self.on_finality_request(&peer, &request, version) {
 					Ok(res) => {
 						log::trace!("enqueueing finality response for peer {}", peer);
 						let mut data = Vec::with_capacity(res.encoded_len());
@@ -274,11 +424,25 @@ where
 					Err(e) => log::debug!("error handling finality request from peer {}: {}", peer, e)
 				}
 			}
-			NodeEvent::Response(response, block_hash) => {
-				let ev = Event::Response {
-					peer,
-					block_hash,
-					proof: response.proof,
+			NodeEvent::Response(response, block_hash, _version) => {
+				// The request has been answered; stop tracking it for timeouts.
+				if response.is_cht {
+					self.pending_cht.remove(&(peer.clone(), response.block_number));
+				} else {
+					self.pending.remove(&block_hash);
+				}
+				let ev = if response.is_cht {
+					Event::ChtProof {
+						peer,
+						block_number: response.block_number,
+						cht_proof: response.cht_proof,
+					}
+				} else {
+					Event::Response {
+						peer,
+						block_hash,
+						proof: response.proof,
+					}
 				};
 				self.pending_events.push_back(NetworkBehaviourAction::GenerateEvent(ev));
 			}
@@ -292,6 +456,66 @@ where
 			return Poll::Ready(ev);
 		}
 
+		// Arm (or re-arm) the timer against the earliest outstanding deadline so
+		// the task is woken when a request expires, even on an otherwise-idle
+		// connection.
+		let now = Instant::now();
+		let earliest = self.pending.values().map(|(_, deadline)| *deadline)
+			.chain(self.pending_cht.values().copied())
+			.min();
+		match (earliest, &self.timeout) {
+			(Some(deadline), Some((armed, _))) if deadline == *armed => {}
+			(Some(deadline), _) => {
+				let delay = Delay::new(deadline.saturating_duration_since(now));
+				self.timeout = Some((deadline, delay));
+			}
+			(None, _) => self.timeout = None,
+		}
+		if let Some((_, delay)) = &mut self.timeout {
+			// Drive the timer; when it fires the expired entry is emitted below
+			// and the timer is re-armed on the next poll.
+			if let Poll::Ready(_) = delay.poll_unpin(cx) {
+				self.timeout = None;
+			}
+		}
+		// Read the clock again so a timer that fired after `now` was taken above
+		// always finds its expired entry.
+		let now = Instant::now();
+
+		// Surface any requests whose deadline has elapsed as `Failure` events so
+		// the caller can retry against a different peer instead of hanging.
+		if let Some(&block_hash) = self.pending
+			.iter()
+			.find(|(_, (_, deadline))| *deadline <= now)
+			.map(|(hash, _)| hash)
+		{
+			let (peer, _) = self.pending.remove(&block_hash)
+				.expect("block hash was just found in the map; qed");
+			log::debug!("finality proof request to {} for {} timed out", peer, block_hash);
+			return Poll::Ready(NetworkBehaviourAction::GenerateEvent(Event::Failure {
+				peer,
+				block_hash,
+				error: From::from("finality proof request timed out".to_string()),
+			}));
+		}
+
+		// Same for CHT-anchored requests, keyed by `(peer, block_number)`.
+		if let Some(key) = self.pending_cht
+			.iter()
+			.find(|(_, deadline)| **deadline <= now)
+			.map(|(key, _)| key.clone())
+		{
+			self.pending_cht.remove(&key)
+				.expect("key was just found in the map; qed");
+			let (peer, block_number) = key;
+			log::debug!("CHT proof request to {} for #{} timed out", peer, block_number);
+			return Poll::Ready(NetworkBehaviourAction::GenerateEvent(Event::ChtFailure {
+				peer,
+				block_number,
+				error: From::from("CHT proof request timed out".to_string()),
+			}));
+		}
+
 		while let Poll::Ready(Some(_)) = self.outgoing.poll_next_unpin(cx) {}
 		Poll::Pending
 	}
@@ -300,10 +524,11 @@ where
 /// Output type of inbound and outbound substream upgrades.
 #[derive(Debug)]
 pub enum NodeEvent<B: Block, T> {
-	/// Incoming request from remote and substream to use for the response.
-	Request(schema::v1::finality::FinalityProofRequest, T),
-	/// Incoming response from remote.
-	Response(schema::v1::finality::FinalityProofResponse, B::Hash),
+	/// Incoming request from remote, the substream to use for the response, and
+	/// the negotiated protocol version.
+	Request(schema::v1::finality::FinalityProofRequest, T, ProtocolVersion),
+	/// Incoming response from remote and the negotiated protocol version.
+	Response(schema::v1::finality::FinalityProofResponse, B::Hash, ProtocolVersion),
 }
 
 /// Substream upgrade protocol.
@@ -316,21 +541,19 @@ pub enum NodeEvent<B: Block, T> {
 pub struct InboundProtocol<B> {
 	/// The max. request length in bytes.
 	max_request_len: usize,
-	/// The protocol to use during upgrade negotiation. If `None`, then the incoming protocol
-	/// is simply disabled.
-	protocol: Option<Bytes>,
+	/// The protocols to advertise during upgrade negotiation, most preferred
+	/// first. An empty list disables the incoming protocol entirely.
+	protocols: Vec<Bytes>,
 	/// Marker to pin the block type.
 	marker: PhantomData<B>,
 }
 
 impl<B: Block> UpgradeInfo for InboundProtocol<B> {
 	type Info = Bytes;
-	// This iterator will return either 0 elements if `self.protocol` is `None`, or 1 element if
-	// it is `Some`.
-	type InfoIter = std::option::IntoIter<Self::Info>;
+	type InfoIter = std::vec::IntoIter<Self::Info>;
 
 	fn protocol_info(&self) -> Self::InfoIter {
-		self.protocol.clone().into_iter()
+		self.protocols.clone().into_iter()
 	}
 }
 
@@ -343,12 +566,16 @@ where
 	type Error = ReadOneError;
 	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
 
-	fn upgrade_inbound(self, mut s: T, _: Self::Info) -> Self::Future {
+	fn upgrade_inbound(self, mut s: T, info: Self::Info) -> Self::Future {
+		let version = protocol_version(&info);
 		async move {
 			let len = self.max_request_len;
 			let vec = read_one(&mut s, len).await?;
+			// Both versions share the protobuf request framing today; the
+			// negotiated version is carried through so the response can be
+			// shaped appropriately.
 			match schema::v1::finality::FinalityProofRequest::decode(&vec[..]) {
-				Ok(r) => Ok(NodeEvent::Request(r, s)),
+				Ok(r) => Ok(NodeEvent::Request(r, s, version)),
 				Err(e) => Err(ReadOneError::Io(io::Error::new(io::ErrorKind::Other, e)))
 			}
 		}.boxed()
@@ -366,16 +593,16 @@ pub struct OutboundProtocol<B: Block> {
 	block_hash: B::Hash,
 	/// The max. response length in bytes.
 	max_response_size: usize,
-	/// The protocol to use for upgrade negotiation.
-	protocol: Bytes,
+	/// The protocols to offer for upgrade negotiation, most preferred first.
+	protocols: Vec<Bytes>,
 }
 
 impl<B: Block> UpgradeInfo for OutboundProtocol<B> {
 	type Info = Bytes;
-	type InfoIter = iter::Once<Self::Info>;
+	type InfoIter = std::vec::IntoIter<Self::Info>;
 
 	fn protocol_info(&self) -> Self::InfoIter {
-		iter::once(self.protocol.clone())
+		self.protocols.clone().into_iter()
 	}
 }
 
@@ -388,13 +615,14 @@ where
 	type Error = ReadOneError;
 	type Future = BoxFuture<'static, Result<Self::Output, Self::Error>>;
 
-	fn upgrade_outbound(self, mut s: T, _: Self::Info) -> Self::Future {
+	fn upgrade_outbound(self, mut s: T, info: Self::Info) -> Self::Future {
+		let version = protocol_version(&info);
 		async move {
 			write_one(&mut s, &self.request).await?;
 			let vec = read_one(&mut s, self.max_response_size).await?;
 
 			schema::v1::finality::FinalityProofResponse::decode(&vec[..])
-				.map(|r| NodeEvent::Response(r, self.block_hash))
+				.map(|r| NodeEvent::Response(r, self.block_hash, version))
 				.map_err(|e| {
 					ReadOneError::Io(io::Error::new(io::ErrorKind::Other, e))
 				})
@@ -0,0 +1,58 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Substrate.
+//
+// Substrate is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Substrate is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Substrate.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Include sources generated from protobuf definitions.
+
+pub mod v1 {
+	pub mod finality {
+		/// Request a finality proof for a given block.
+		#[derive(Clone, PartialEq, ::prost::Message)]
+		pub struct FinalityProofRequest {
+			/// SCALE-encoded hash of the block to prove finality of.
+			#[prost(bytes, tag = "1")]
+			pub block_hash: std::vec::Vec<u8>,
+			/// Opaque, finality-engine specific request payload.
+			#[prost(bytes, tag = "2")]
+			pub request: std::vec::Vec<u8>,
+			/// Whether this request should be answered from a Canonical Hash Trie
+			/// rather than with a full justification.
+			#[prost(bool, tag = "3")]
+			pub is_cht: bool,
+			/// Block number to anchor a CHT proof to. Only meaningful when `is_cht`.
+			#[prost(uint32, tag = "4")]
+			pub block_number: u32,
+		}
+
+		/// Response to a finality proof request.
+		#[derive(Clone, PartialEq, ::prost::Message)]
+		pub struct FinalityProofResponse {
+			/// Opaque, finality-engine specific proof payload (empty if none).
+			#[prost(bytes, tag = "1")]
+			pub proof: std::vec::Vec<u8>,
+			/// Whether this response carries a CHT-anchored proof.
+			#[prost(bool, tag = "2")]
+			pub is_cht: bool,
+			/// Block number the CHT proof is anchored to. Only meaningful when `is_cht`.
+			#[prost(uint32, tag = "3")]
+			pub block_number: u32,
+			/// Merkle path nodes from the CHT root down to the `number => hash` leaf.
+			///
+			/// Empty when the target range is not yet a complete, finalized CHT.
+			#[prost(bytes, repeated, tag = "4")]
+			pub cht_proof: std::vec::Vec<std::vec::Vec<u8>>,
+		}
+	}
+}
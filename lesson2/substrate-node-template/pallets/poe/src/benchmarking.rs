@@ -0,0 +1,70 @@
+//! Benchmarking for the PoE pallet.
+
+#![cfg(feature = "runtime-benchmarks")]
+
+use super::*;
+use crate::Module as Poe;
+use frame_benchmarking::{benchmarks, account, whitelisted_caller};
+use frame_system::RawOrigin;
+use sp_std::prelude::*;
+
+/// Build a proof of the given length, filled with deterministic bytes.
+fn proof_of_len(len: u32) -> Vec<u8> {
+	vec![0u8; len as usize]
+}
+
+const SEED: u32 = 0;
+
+benchmarks! {
+	_ { }
+
+	create_claim {
+		// the proof length, bounded by the pallet's maximum.
+		let p in 0 .. MAX_PROOF_SIZE;
+		let caller: T::AccountId = whitelisted_caller();
+		let proof = proof_of_len(p);
+	}: _(RawOrigin::Signed(caller), proof.clone())
+	verify {
+		assert!(Proofs::<T>::contains_key(&proof));
+	}
+
+	revoke_claim {
+		let p in 0 .. MAX_PROOF_SIZE;
+		let caller: T::AccountId = whitelisted_caller();
+		let proof = proof_of_len(p);
+		let block = <system::Module<T>>::block_number();
+		Proofs::<T>::insert(&proof, (caller.clone(), block));
+	}: _(RawOrigin::Signed(caller), proof.clone())
+	verify {
+		assert!(!Proofs::<T>::contains_key(&proof));
+	}
+
+	transfer_claim {
+		let p in 0 .. MAX_PROOF_SIZE;
+		let caller: T::AccountId = whitelisted_caller();
+		let receiver: T::AccountId = account("receiver", 0, SEED);
+		let proof = proof_of_len(p);
+		let block = <system::Module<T>>::block_number();
+		Proofs::<T>::insert(&proof, (caller.clone(), block));
+	}: _(RawOrigin::Signed(caller), proof.clone(), receiver.clone())
+	verify {
+		let (owner, _) = Proofs::<T>::get(&proof);
+		assert_eq!(owner, receiver);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::mock::{new_test_ext, Test};
+	use frame_support::assert_ok;
+
+	#[test]
+	fn test_benchmarks() {
+		new_test_ext().execute_with(|| {
+			assert_ok!(test_benchmark_create_claim::<Test>());
+			assert_ok!(test_benchmark_revoke_claim::<Test>());
+			assert_ok!(test_benchmark_transfer_claim::<Test>());
+		});
+	}
+}
@@ -0,0 +1,32 @@
+//! Default weights for the PoE pallet.
+//!
+//! These are linear approximations of the form `base + per_byte * proof_len`,
+//! so the `MAX_PROOF_SIZE` bound can be raised without re-guessing the weight.
+//! Integrators should replace the `()` implementation with figures produced by
+//! the `runtime-benchmarks` feature for their own hardware.
+
+use frame_support::weights::{Weight, constants::RocksDbWeight as DbWeight};
+
+impl crate::WeightInfo for () {
+	fn create_claim(p: u32) -> Weight {
+		// base cost of the extrinsic plus a per-byte term for hashing the proof.
+		(40_000_000 as Weight)
+			.saturating_add((5_000 as Weight).saturating_mul(p as Weight))
+			// Reads: `Proofs`.
+			.saturating_add(DbWeight::get().reads(1 as Weight))
+			// Writes: `Proofs`.
+			.saturating_add(DbWeight::get().writes(1 as Weight))
+	}
+
+	fn revoke_claim() -> Weight {
+		(40_000_000 as Weight)
+			.saturating_add(DbWeight::get().reads(1 as Weight))
+			.saturating_add(DbWeight::get().writes(1 as Weight))
+	}
+
+	fn transfer_claim() -> Weight {
+		(45_000_000 as Weight)
+			.saturating_add(DbWeight::get().reads(1 as Weight))
+			.saturating_add(DbWeight::get().writes(1 as Weight))
+	}
+}
@@ -0,0 +1,172 @@
+//! Gossip-based propagation of proof claims.
+//!
+//! The PoE pallet only writes claims into the `Proofs` storage map, so peers
+//! and off-chain indexers have no way to learn about new claims without reading
+//! full chain state. This module registers a notifications protocol and gives
+//! nodes a push-based view of claim activity: whenever a `ClaimCreated` or
+//! `ClaimTransferred` event fires, a compact announcement is broadcast to the
+//! network, and receiving nodes can index it locally for fast existence lookups.
+//!
+//! This is the client (`std`) side of the pallet and is therefore only compiled
+//! when the `std` feature is enabled.
+
+use std::{borrow::Cow, collections::HashSet, marker::PhantomData, sync::Arc};
+
+use codec::{Decode, Encode};
+use parking_lot::Mutex;
+use sc_network::PeerId;
+use sc_network_gossip::{GossipEngine, MessageIntent, ValidationResult, Validator, ValidatorContext};
+use sp_runtime::traits::{Block as BlockT, Hash};
+
+/// Name of the notifications protocol used to gossip claim announcements.
+pub const PROTOCOL_NAME: Cow<'static, str> = Cow::Borrowed("/poe/claims/1");
+
+/// A compact announcement broadcast whenever a claim is created or transferred.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct ClaimAnnouncement<AccountId, BlockNumber> {
+	/// Hash of the claimed proof (the `Proofs` key, hashed for a fixed-width id).
+	pub proof_hash: Vec<u8>,
+	/// The account that currently owns the claim.
+	pub owner: AccountId,
+	/// The block number at which the claim was (re)assigned.
+	pub block_number: BlockNumber,
+}
+
+/// The gossip topic all claim announcements are published on.
+fn topic<B: BlockT>() -> B::Hash {
+	<<B::Header as sp_runtime::traits::Header>::Hashing as Hash>::hash(b"poe-claims")
+}
+
+/// Handle used to broadcast claim announcements onto the network.
+///
+/// A node's service task builds one of these from its `GossipEngine` and calls
+/// [`ClaimGossip::announce`] for every `ClaimCreated`/`ClaimTransferred` event it
+/// observes, so claim activity is pushed to peers instead of only being written
+/// to storage.
+pub struct ClaimGossip<B: BlockT> {
+	engine: Arc<Mutex<GossipEngine<B>>>,
+}
+
+impl<B: BlockT> ClaimGossip<B> {
+	/// Wrap a shared `GossipEngine` registered for [`PROTOCOL_NAME`].
+	pub fn new(engine: Arc<Mutex<GossipEngine<B>>>) -> Self {
+		ClaimGossip { engine }
+	}
+
+	/// Broadcast an announcement for a claim that was just created or transferred.
+	///
+	/// The raw `proof` is hashed down to a fixed-width id before going on the
+	/// wire so the announcement stays compact regardless of proof length.
+	pub fn announce<AccountId, BlockNumber>(
+		&self,
+		proof: &[u8],
+		owner: AccountId,
+		block_number: BlockNumber,
+	)
+	where
+		AccountId: Encode,
+		BlockNumber: Encode,
+	{
+		let announcement = ClaimAnnouncement {
+			proof_hash: sp_core::blake2_128(proof).to_vec(),
+			owner,
+			block_number,
+		};
+		self.engine.lock().gossip_message(topic::<B>(), announcement.encode(), false);
+	}
+}
+
+/// Broadcast the claim activity carried by a PoE `RawEvent`, if any.
+///
+/// This is the hook a node's service task wires into its event stream: it maps a
+/// `ClaimCreated`/`ClaimTransferred` event emitted by the `create_claim` /
+/// `transfer_claim` extrinsics onto a gossip announcement. Other events are
+/// ignored.
+pub fn announce_event<B, T>(
+	gossip: &ClaimGossip<B>,
+	event: &crate::RawEvent<T::AccountId>,
+	block_number: T::BlockNumber,
+)
+where
+	B: BlockT,
+	T: crate::Trait,
+{
+	match event {
+		crate::RawEvent::ClaimCreated(owner, proof) =>
+			gossip.announce::<_, T::BlockNumber>(proof, owner.clone(), block_number),
+		// The receiver becomes the new owner of a transferred claim.
+		crate::RawEvent::ClaimTransferred(_, proof, receiver) =>
+			gossip.announce::<_, T::BlockNumber>(proof, receiver.clone(), block_number),
+		_ => {}
+	}
+}
+
+/// Gossip validator for claim announcements.
+///
+/// Drops announcements for hashes we have already seen so a node only
+/// rebroadcasts genuinely new claim activity. The seen set is keyed by
+/// `proof_hash` alone and is never pruned, so a later announcement for a proof
+/// that was already seen, such as the transfer of a claim whose creation was
+/// already relayed, is dropped as well. Note that `proof_hash` is a
+/// fixed-width digest, so its length carries no information about the source
+/// proof size and `MAX_PROOF_SIZE` is enforced on-chain, not here.
+///
+/// The `AccountId`/`BlockNumber` parameters fix the announcement payload types a
+/// given validator instance decodes.
+pub struct ClaimGossipValidator<AccountId, BlockNumber> {
+	seen: Mutex<HashSet<Vec<u8>>>,
+	marker: PhantomData<(AccountId, BlockNumber)>,
+}
+
+impl<AccountId, BlockNumber> ClaimGossipValidator<AccountId, BlockNumber> {
+	/// Create a fresh validator with an empty set of seen hashes.
+	pub fn new() -> Self {
+		ClaimGossipValidator {
+			seen: Mutex::new(HashSet::new()),
+			marker: PhantomData,
+		}
+	}
+}
+
+impl<AccountId, BlockNumber> Default for ClaimGossipValidator<AccountId, BlockNumber> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<B, AccountId, BlockNumber> Validator<B> for ClaimGossipValidator<AccountId, BlockNumber>
+where
+	B: BlockT,
+	AccountId: Decode + Send + Sync + 'static,
+	BlockNumber: Decode + Send + Sync + 'static,
+{
+	fn validate(
+		&self,
+		_context: &mut dyn ValidatorContext<B>,
+		_sender: &PeerId,
+		data: &[u8],
+	) -> ValidationResult<B::Hash> {
+		let announcement = match ClaimAnnouncement::<AccountId, BlockNumber>::decode(&mut &data[..]) {
+			Ok(a) => a,
+			Err(_) => return ValidationResult::Discard,
+		};
+
+		// Drop announcements we have already relayed.
+		let mut seen = self.seen.lock();
+		if !seen.insert(announcement.proof_hash.clone()) {
+			return ValidationResult::Discard;
+		}
+
+		ValidationResult::ProcessAndKeep(topic::<B>())
+	}
+
+	fn message_expired<'a>(&'a self) -> Box<dyn FnMut(B::Hash, &[u8]) -> bool + 'a> {
+		Box::new(move |_topic, _data| false)
+	}
+
+	fn message_allowed<'a>(
+		&'a self,
+	) -> Box<dyn FnMut(&PeerId, MessageIntent, &B::Hash, &[u8]) -> bool + 'a> {
+		Box::new(move |_who, _intent, _topic, _data| true)
+	}
+}
@@ -1,6 +1,7 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 use frame_support::{decl_error, decl_event, decl_module, decl_storage, ensure, StorageMap};
+use frame_support::weights::Weight;
 use frame_system::{self as system, ensure_signed};
 use sp_std::prelude::Vec;
 
@@ -10,10 +11,33 @@ mod mock;
 #[cfg(test)]
 mod tests;
 
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
+#[cfg(feature = "std")]
+pub mod gossip;
+
+pub mod default_weights;
+
+/// The maximum number of bytes a proof may contain.
+///
+/// Benchmarks vary the proof length up to this bound; raising it only requires
+/// re-running the benchmark template, not re-guessing the flat weight.
+pub const MAX_PROOF_SIZE: u32 = 9;
+
+/// Weight functions needed for this pallet.
+pub trait WeightInfo {
+	fn create_claim(p: u32) -> Weight;
+	fn revoke_claim() -> Weight;
+	fn transfer_claim() -> Weight;
+}
+
 /// The pallet's configuration trait.
 pub trait Trait: system::Trait {
 	/// The overarching event type.
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	/// Weight information for the extrinsics in this pallet.
+	type WeightInfo: WeightInfo;
 }
 
 // This pallet's storage items.
@@ -66,18 +90,17 @@ decl_module! {
 		fn deposit_event() = default;
 
 
-		#[weight = 10_000]
+		#[weight = T::WeightInfo::create_claim(proof.len() as u32)]
 		fn create_claim(origin, proof: Vec<u8>) {
-			const MAX_PROOF_SIZE: usize = 9;
 			let sender = ensure_signed(origin)?;
-			ensure!(proof.len() <= MAX_PROOF_SIZE, Error::<T>::ProofTooLarge);
+			ensure!(proof.len() as u32 <= MAX_PROOF_SIZE, Error::<T>::ProofTooLarge);
 			ensure!(!Proofs::<T>::contains_key(&proof), Error::<T>::ProofAlreadyClaimed);
 			let current_block = <system::Module<T>>::block_number();
 			Proofs::<T>::insert(&proof, (sender.clone(), current_block));
 			Self::deposit_event(RawEvent::ClaimCreated(sender, proof));
 		}
 
-		#[weight = 10_000]
+		#[weight = T::WeightInfo::revoke_claim()]
 		fn revoke_claim(origin, proof: Vec<u8>) {
 			let sender = ensure_signed(origin)?;
 			ensure!(Proofs::<T>::contains_key(&proof), Error::<T>::NoSuchProof);
@@ -87,7 +110,7 @@ decl_module! {
 			Self::deposit_event(RawEvent::ClaimRevoked(sender, proof));
 		}
 
-		#[weight = 10_000]
+		#[weight = T::WeightInfo::transfer_claim()]
 		fn transfer_claim(origin, proof: Vec<u8>, receiver: T::AccountId) {
 			let sender = ensure_signed(origin)?;
 			ensure!(Proofs::<T>::contains_key(&proof), Error::<T>::NoSuchProof);